@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::fmt;
 use std::fs::File;
 use std::future::Future;
@@ -8,13 +10,21 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::watch;
 
-use futures_util::ready;
+use futures_util::{future, ready};
 use hyper::server::accept::Accept;
 use hyper::server::conn::{AddrIncoming, AddrStream};
-use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::crypto::CryptoProvider;
+use tokio_rustls::rustls::pki_types::{
+    CertificateDer, CertificateRevocationListDer, PrivateKeyDer,
+};
+use tokio_rustls::rustls::server::danger::ClientCertVerifier;
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use tokio_rustls::rustls::sign::CertifiedKey;
 use tokio_rustls::rustls::{Error as TlsError, RootCertStore, ServerConfig};
 
+use crate::filter::{filter_fn_one, Filter};
 use crate::transport::Transport;
 
 /// Represents errors that can occur building the TlsConfig
@@ -67,12 +77,22 @@ pub(crate) enum TlsClientAuth {
     Required(Box<dyn Read + Send + Sync>),
 }
 
+/// A hostname paired with the certificate chain and key to present for it via SNI.
+type SniCertEntry = (
+    String,
+    Box<dyn Read + Send + Sync>,
+    Box<dyn Read + Send + Sync>,
+);
+
 /// Builder to set the configuration for the Tls server.
 pub(crate) struct TlsConfigBuilder {
     cert: Box<dyn Read + Send + Sync>,
     key: Box<dyn Read + Send + Sync>,
     client_auth: TlsClientAuth,
     ocsp_resp: Vec<u8>,
+    sni_certs: Vec<SniCertEntry>,
+    alpn_protocols: Option<Vec<Vec<u8>>>,
+    crls: Vec<Box<dyn Read + Send + Sync>>,
 }
 
 impl fmt::Debug for TlsConfigBuilder {
@@ -89,6 +109,9 @@ impl TlsConfigBuilder {
             cert: Box::new(io::empty()),
             client_auth: TlsClientAuth::Off,
             ocsp_resp: Vec::new(),
+            sni_certs: Vec::new(),
+            alpn_protocols: None,
+            crls: Vec::new(),
         }
     }
 
@@ -122,6 +145,30 @@ impl TlsConfigBuilder {
         self
     }
 
+    /// Registers an additional certificate/key pair to be served via SNI for `hostname`.
+    ///
+    /// Calling this one or more times switches the server into SNI-based virtual
+    /// hosting: instead of always presenting the single certificate configured via
+    /// [`cert`]/[`cert_path`], the certificate chain returned to the client is chosen
+    /// by matching the `server_name` from its `ClientHello` against the hostnames
+    /// registered here. This lets one warp TLS listener terminate many domains.
+    ///
+    /// [`cert`]: TlsConfigBuilder::cert
+    /// [`cert_path`]: TlsConfigBuilder::cert_path
+    pub(crate) fn add_sni_cert(
+        mut self,
+        hostname: impl Into<String>,
+        cert: &[u8],
+        key: &[u8],
+    ) -> Self {
+        self.sni_certs.push((
+            hostname.into(),
+            Box::new(Cursor::new(Vec::from(cert))),
+            Box::new(Cursor::new(Vec::from(key))),
+        ));
+        self
+    }
+
     /// Sets the trust anchor for optional Tls client authentication via file path.
     ///
     /// Anonymous and authenticated clients will be accepted. If no trust anchor is provided by any
@@ -168,72 +215,278 @@ impl TlsConfigBuilder {
         self
     }
 
+    /// Adds a certificate revocation list (PEM or DER) used to reject revoked client
+    /// certificates during `client_auth_optional`/`client_auth_required` verification,
+    /// loaded from a file path. May be called more than once to supply several CRLs.
+    pub(crate) fn client_auth_crl_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.crls.push(Box::new(LazyFile {
+            path: path.as_ref().into(),
+            file: None,
+        }));
+        self
+    }
+
+    /// Adds a certificate revocation list (PEM or DER) used to reject revoked client
+    /// certificates during `client_auth_optional`/`client_auth_required` verification,
+    /// loaded from bytes. May be called more than once to supply several CRLs.
+    pub(crate) fn client_auth_crl(mut self, crl: &[u8]) -> Self {
+        self.crls.push(Box::new(Cursor::new(Vec::from(crl))));
+        self
+    }
+
     /// sets the DER-encoded OCSP response
     pub(crate) fn ocsp_resp(mut self, ocsp_resp: &[u8]) -> Self {
         self.ocsp_resp = Vec::from(ocsp_resp);
         self
     }
 
+    /// Sets the ALPN protocols to advertise during the Tls handshake, in preference order.
+    ///
+    /// Defaults to `["h2", "http/1.1"]` when unset.
+    pub(crate) fn alpn_protocols(mut self, protocols: Vec<Vec<u8>>) -> Self {
+        self.alpn_protocols = Some(protocols);
+        self
+    }
+
+    /// Advertises only `http/1.1`, opting the connection out of HTTP/2 negotiation.
+    ///
+    /// Useful for buggy intermediaries that mishandle `h2` ALPN advertisement.
+    pub(crate) fn disable_http2(mut self) -> Self {
+        self.alpn_protocols = Some(vec!["http/1.1".into()]);
+        self
+    }
+
+    /// Builds the `ServerConfig`, discarding the `TlsConfigTemplate` that
+    /// would be needed to reload it later. See [`build_with_template`] if the
+    /// config needs to support [`TlsReloadHandle::reload`].
+    ///
+    /// [`build_with_template`]: TlsConfigBuilder::build_with_template
     pub(crate) fn build(self) -> Result<ServerConfig, TlsConfigError> {
-        let mut cert_rdr = BufReader::new(self.cert);
-        let cert = rustls_pemfile::certs(&mut cert_rdr)
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|_e| TlsConfigError::CertParseError)?;
+        self.build_with_template().map(|(config, _template)| config)
+    }
 
-        let mut key_rdr = BufReader::new(self.key);
-        let key = rustls_pemfile::private_key(&mut key_rdr)
-            .map_err(TlsConfigError::Io)?
-            .ok_or(TlsConfigError::MissingPrivateKey)?;
-
-        fn read_trust_anchor(
-            trust_anchor: Box<dyn Read + Send + Sync>,
-        ) -> Result<RootCertStore, TlsConfigError> {
-            let trust_anchors = {
-                let mut reader = BufReader::new(trust_anchor);
-                rustls_pemfile::certs(&mut reader)
-                    .collect::<Result<Vec<_>, _>>()
-                    .map_err(TlsConfigError::Io)?
-            };
+    /// Builds the `ServerConfig`, plus a `TlsConfigTemplate` capturing
+    /// everything but the leaf certificate/key. `TlsAcceptor::new` uses the
+    /// template so that a later `TlsReloadHandle::reload` can rebuild the
+    /// config without losing client auth, CRLs, ALPN, or SNI settings.
+    pub(crate) fn build_with_template(
+        mut self,
+    ) -> Result<(ServerConfig, TlsConfigTemplate), TlsConfigError> {
+        let crls = read_crls(self.crls)?;
+        let provider = ServerConfig::builder().crypto_provider().clone();
+
+        let client_verifier = match self.client_auth {
+            TlsClientAuth::Off => None,
+            TlsClientAuth::Optional(trust_anchor) => Some(
+                WebPkiClientVerifier::builder(read_trust_anchor(trust_anchor)?.into())
+                    .with_crls(crls)
+                    .allow_unauthenticated()
+                    .build()
+                    .map_err(|_| TlsConfigError::CertParseError)?,
+            ),
+            TlsClientAuth::Required(trust_anchor) => Some(
+                WebPkiClientVerifier::builder(read_trust_anchor(trust_anchor)?.into())
+                    .with_crls(crls)
+                    .build()
+                    .map_err(|_| TlsConfigError::CertParseError)?,
+            ),
+        };
 
-            let mut store = RootCertStore::empty();
-            let (added, _skipped) = store.add_parsable_certificates(trust_anchors);
-            if added == 0 {
+        let alpn_protocols = self
+            .alpn_protocols
+            .unwrap_or_else(|| vec!["h2".into(), "http/1.1".into()]);
+
+        let mut sni_certs = HashMap::with_capacity(self.sni_certs.len());
+        for (hostname, cert, key) in self.sni_certs {
+            sni_certs.insert(
+                hostname,
+                Arc::new(read_certified_key(cert, key, &provider)?),
+            );
+        }
+
+        let mut default_cert = Vec::new();
+        self.cert
+            .read_to_end(&mut default_cert)
+            .map_err(TlsConfigError::Io)?;
+        let mut default_key = Vec::new();
+        self.key
+            .read_to_end(&mut default_key)
+            .map_err(TlsConfigError::Io)?;
+
+        if sni_certs.is_empty() {
+            if default_key.is_empty() {
+                return Err(TlsConfigError::MissingPrivateKey);
+            }
+            if default_cert.is_empty() {
                 return Err(TlsConfigError::CertParseError);
             }
+        }
 
-            Ok(store)
+        let template = TlsConfigTemplate {
+            client_verifier,
+            alpn_protocols,
+            sni_certs,
+            provider,
+        };
+        let config = template.build_server_config(&default_cert, &default_key, self.ocsp_resp)?;
+        Ok((config, template))
+    }
+}
+
+fn read_trust_anchor(
+    trust_anchor: Box<dyn Read + Send + Sync>,
+) -> Result<RootCertStore, TlsConfigError> {
+    let trust_anchors = {
+        let mut reader = BufReader::new(trust_anchor);
+        rustls_pemfile::certs(&mut reader)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(TlsConfigError::Io)?
+    };
+
+    let mut store = RootCertStore::empty();
+    let (added, _skipped) = store.add_parsable_certificates(trust_anchors);
+    if added == 0 {
+        return Err(TlsConfigError::CertParseError);
+    }
+
+    Ok(store)
+}
+
+fn read_crls(
+    crls: Vec<Box<dyn Read + Send + Sync>>,
+) -> Result<Vec<CertificateRevocationListDer<'static>>, TlsConfigError> {
+    let mut all = Vec::new();
+    for mut crl in crls {
+        let mut bytes = Vec::new();
+        crl.read_to_end(&mut bytes).map_err(TlsConfigError::Io)?;
+        let parsed = rustls_pemfile::crls(&mut bytes.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(TlsConfigError::Io)?;
+        if parsed.is_empty() && !bytes.is_empty() {
+            // No PEM sections found: treat the input as a single DER-encoded CRL.
+            all.push(CertificateRevocationListDer::from(bytes));
+        } else {
+            all.extend(parsed);
         }
+    }
+    Ok(all)
+}
 
-        let config = {
-            let builder = ServerConfig::builder();
-            let mut config = match self.client_auth {
-                TlsClientAuth::Off => builder.with_no_client_auth(),
-                TlsClientAuth::Optional(trust_anchor) => {
-                    let verifier =
-                        WebPkiClientVerifier::builder(read_trust_anchor(trust_anchor)?.into())
-                            .allow_unauthenticated()
-                            .build()
-                            .map_err(|_| TlsConfigError::CertParseError)?;
-                    builder.with_client_cert_verifier(verifier)
-                }
-                TlsClientAuth::Required(trust_anchor) => {
-                    let verifier =
-                        WebPkiClientVerifier::builder(read_trust_anchor(trust_anchor)?.into())
-                            .build()
-                            .map_err(|_| TlsConfigError::CertParseError)?;
-                    builder.with_client_cert_verifier(verifier)
-                }
-            }
-            .with_single_cert_with_ocsp(cert, key, self.ocsp_resp)
-            .map_err(TlsConfigError::InvalidKey)?;
-            config.alpn_protocols = vec!["h2".into(), "http/1.1".into()];
-            config
+fn parse_cert_chain(
+    cert: Box<dyn Read + Send + Sync>,
+) -> Result<Vec<CertificateDer<'static>>, TlsConfigError> {
+    let mut reader = BufReader::new(cert);
+    rustls_pemfile::certs(&mut reader)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_e| TlsConfigError::CertParseError)
+}
+
+fn parse_private_key(
+    key: Box<dyn Read + Send + Sync>,
+) -> Result<PrivateKeyDer<'static>, TlsConfigError> {
+    let mut reader = BufReader::new(key);
+    rustls_pemfile::private_key(&mut reader)
+        .map_err(TlsConfigError::Io)?
+        .ok_or(TlsConfigError::MissingPrivateKey)
+}
+
+/// Parses a certificate chain and private key and resolves them against a
+/// `CryptoProvider` that's already known to be valid, rather than reaching
+/// for the process-global default: this is used for the SNI resolver, where
+/// there's no other fallible step to surface a missing provider through.
+fn read_certified_key(
+    cert: Box<dyn Read + Send + Sync>,
+    key: Box<dyn Read + Send + Sync>,
+    provider: &CryptoProvider,
+) -> Result<CertifiedKey, TlsConfigError> {
+    let cert_chain = parse_cert_chain(cert)?;
+    let key = parse_private_key(key)?;
+    CertifiedKey::from_der(cert_chain, key, provider).map_err(TlsConfigError::InvalidKey)
+}
+
+/// The parts of a Tls configuration that stay valid across a certificate/key
+/// reload: client authentication (and its CRLs), ALPN protocols, SNI
+/// certificates, and the resolved crypto provider.
+pub(crate) struct TlsConfigTemplate {
+    client_verifier: Option<Arc<dyn ClientCertVerifier>>,
+    alpn_protocols: Vec<Vec<u8>>,
+    sni_certs: HashMap<String, Arc<CertifiedKey>>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl TlsConfigTemplate {
+    /// Builds a fresh `ServerConfig` from this template plus a default
+    /// certificate/key pair.
+    ///
+    /// If SNI certificates were registered, a non-empty `cert`/`key` is used
+    /// as the fallback presented to clients that don't send SNI or ask for a
+    /// hostname that wasn't registered via `add_sni_cert`; an empty `cert`/
+    /// `key` leaves SNI mode without a fallback, same as if neither had been
+    /// configured. Otherwise `cert`/`key` are the server's only certificate.
+    pub(crate) fn build_server_config(
+        &self,
+        cert: &[u8],
+        key: &[u8],
+        ocsp_resp: Vec<u8>,
+    ) -> Result<ServerConfig, TlsConfigError> {
+        let builder = ServerConfig::builder();
+        let builder = match &self.client_verifier {
+            Some(verifier) => builder.with_client_cert_verifier(verifier.clone()),
+            None => builder.with_no_client_auth(),
         };
 
+        let mut config = if self.sni_certs.is_empty() {
+            builder
+                .with_single_cert_with_ocsp(
+                    parse_cert_chain(Box::new(Cursor::new(Vec::from(cert))))?,
+                    parse_private_key(Box::new(Cursor::new(Vec::from(key))))?,
+                    ocsp_resp,
+                )
+                .map_err(TlsConfigError::InvalidKey)?
+        } else {
+            let default = if cert.is_empty() || key.is_empty() {
+                None
+            } else {
+                Some(Arc::new(read_certified_key(
+                    Box::new(Cursor::new(Vec::from(cert))),
+                    Box::new(Cursor::new(Vec::from(key))),
+                    &self.provider,
+                )?))
+            };
+            builder.with_cert_resolver(Arc::new(SniCertResolver {
+                certs: self.sni_certs.clone(),
+                default,
+            }))
+        };
+        config.alpn_protocols = self.alpn_protocols.clone();
         Ok(config)
     }
 }
 
+/// Resolves the certificate to present based on the SNI hostname requested by
+/// the client, enabling a single listener to terminate TLS for multiple
+/// domains. Falls back to `default` when the client doesn't send SNI, or asks
+/// for a hostname that wasn't registered via `add_sni_cert`.
+struct SniCertResolver {
+    certs: HashMap<String, Arc<CertifiedKey>>,
+    default: Option<Arc<CertifiedKey>>,
+}
+
+impl fmt::Debug for SniCertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SniCertResolver").finish()
+    }
+}
+
+impl ResolvesServerCert for SniCertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        client_hello
+            .server_name()
+            .and_then(|hostname| self.certs.get(hostname).cloned())
+            .or_else(|| self.default.clone())
+    }
+}
+
 struct LazyFile {
     path: PathBuf,
     file: Option<File>,
@@ -265,6 +518,24 @@ impl Transport for TlsStream {
     fn remote_addr(&self) -> Option<SocketAddr> {
         Some(self.remote_addr)
     }
+
+    fn peer_certificates(&self) -> Option<Vec<CertificateDer<'static>>> {
+        self.peer_certs.clone()
+    }
+}
+
+/// Creates a `Filter` that extracts the peer's Tls client certificate chain
+/// for the current connection, if any.
+///
+/// Yields `None` on a plaintext connection, and on a Tls connection where the
+/// client didn't present a certificate (no client auth configured, or
+/// `client_auth_optional` without a cert). Reads from the same per-connection
+/// `Transport::peer_certificates()` that `TlsStream` populates in
+/// `capture_peer_certs`, the same way `warp::filters::addr::remote()` reads
+/// `Transport::remote_addr()`.
+pub(crate) fn peer_certificates(
+) -> impl Filter<Extract = (Option<Vec<CertificateDer<'static>>>,), Error = Infallible> + Copy {
+    filter_fn_one(|route| future::ok(route.peer_certificates()))
 }
 
 enum State {
@@ -278,6 +549,7 @@ enum State {
 pub(crate) struct TlsStream {
     state: State,
     remote_addr: SocketAddr,
+    peer_certs: Option<Vec<CertificateDer<'static>>>,
 }
 
 impl TlsStream {
@@ -287,8 +559,20 @@ impl TlsStream {
         TlsStream {
             state: State::Handshaking(accept),
             remote_addr,
+            peer_certs: None,
         }
     }
+
+    // The client's certificate chain is only known once the handshake has
+    // completed, so we snapshot it the moment we transition into
+    // `State::Streaming` and cache it for the lifetime of the connection.
+    fn capture_peer_certs(&mut self, stream: &tokio_rustls::server::TlsStream<AddrStream>) {
+        self.peer_certs = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .map(|certs| certs.to_vec());
+    }
 }
 
 impl AsyncRead for TlsStream {
@@ -302,6 +586,7 @@ impl AsyncRead for TlsStream {
             State::Handshaking(ref mut accept) => match ready!(Pin::new(accept).poll(cx)) {
                 Ok(mut stream) => {
                     let result = Pin::new(&mut stream).poll_read(cx, buf);
+                    pin.capture_peer_certs(&stream);
                     pin.state = State::Streaming(stream);
                     result
                 }
@@ -323,6 +608,7 @@ impl AsyncWrite for TlsStream {
             State::Handshaking(ref mut accept) => match ready!(Pin::new(accept).poll(cx)) {
                 Ok(mut stream) => {
                     let result = Pin::new(&mut stream).poll_write(cx, buf);
+                    pin.capture_peer_certs(&stream);
                     pin.state = State::Streaming(stream);
                     result
                 }
@@ -347,17 +633,49 @@ impl AsyncWrite for TlsStream {
     }
 }
 
+/// A handle that lets the application swap in a freshly built `ServerConfig`
+/// at runtime, e.g. after a certificate renewal, without dropping the listener
+/// or any in-flight connections.
+#[derive(Clone)]
+pub(crate) struct TlsReloadHandle {
+    config: watch::Sender<Arc<ServerConfig>>,
+    template: Arc<TlsConfigTemplate>,
+}
+
+impl TlsReloadHandle {
+    /// Rebuilds the Tls configuration from the given cert/key, reusing the
+    /// client authentication, CRLs, ALPN, and SNI settings the acceptor was
+    /// originally constructed with, and atomically replaces the config used
+    /// for all connections accepted from this point on.
+    pub(crate) fn reload(&self, cert: &[u8], key: &[u8]) -> Result<(), TlsConfigError> {
+        let config = self.template.build_server_config(cert, key, Vec::new())?;
+        let _ = self.config.send(Arc::new(config));
+        Ok(())
+    }
+}
+
 pub(crate) struct TlsAcceptor {
-    config: Arc<ServerConfig>,
+    config: watch::Receiver<Arc<ServerConfig>>,
     incoming: AddrIncoming,
 }
 
 impl TlsAcceptor {
-    pub(crate) fn new(config: ServerConfig, incoming: AddrIncoming) -> TlsAcceptor {
-        TlsAcceptor {
-            config: Arc::new(config),
-            incoming,
-        }
+    pub(crate) fn new(
+        builder: TlsConfigBuilder,
+        incoming: AddrIncoming,
+    ) -> Result<(TlsAcceptor, TlsReloadHandle), TlsConfigError> {
+        let (config, template) = builder.build_with_template()?;
+        let (tx, rx) = watch::channel(Arc::new(config));
+        Ok((
+            TlsAcceptor {
+                config: rx,
+                incoming,
+            },
+            TlsReloadHandle {
+                config: tx,
+                template: Arc::new(template),
+            },
+        ))
     }
 }
 
@@ -371,7 +689,10 @@ impl Accept for TlsAcceptor {
     ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
         let pin = self.get_mut();
         match ready!(Pin::new(&mut pin.incoming).poll_accept(cx)) {
-            Some(Ok(sock)) => Poll::Ready(Some(Ok(TlsStream::new(sock, pin.config.clone())))),
+            Some(Ok(sock)) => {
+                let config = pin.config.borrow().clone();
+                Poll::Ready(Some(Ok(TlsStream::new(sock, config))))
+            }
             Some(Err(e)) => Poll::Ready(Some(Err(e))),
             None => Poll::Ready(None),
         }
@@ -381,6 +702,88 @@ impl Accept for TlsAcceptor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio_rustls::rustls::{ClientConfig, ClientConnection, ServerConnection};
+
+    /// Builds a `RootCertStore` trusting `certs` (PEM-encoded), for use as a
+    /// test client's trust anchor.
+    fn trust_roots(certs: &[&[u8]]) -> RootCertStore {
+        let mut roots = RootCertStore::empty();
+        for cert in certs {
+            roots.add_parsable_certificates(
+                parse_cert_chain(Box::new(Cursor::new(Vec::from(*cert)))).unwrap(),
+            );
+        }
+        roots
+    }
+
+    /// Drives a full client/server handshake in-process (no sockets), so
+    /// tests can exercise client certificate verification, revocation, and
+    /// SNI cert selection the same way `TlsStream` does, without needing a
+    /// running listener.
+    fn handshake(
+        server_config: Arc<ServerConfig>,
+        client_config: Arc<ClientConfig>,
+        server_name: &str,
+    ) -> Result<(ClientConnection, ServerConnection), TlsError> {
+        let mut client =
+            ClientConnection::new(client_config, server_name.to_string().try_into().unwrap())
+                .unwrap();
+        let mut server = ServerConnection::new(server_config).unwrap();
+
+        while client.is_handshaking() || server.is_handshaking() {
+            let mut client_to_server = Vec::new();
+            if client.wants_write() {
+                client.write_tls(&mut client_to_server).unwrap();
+            }
+            if !client_to_server.is_empty() {
+                server.read_tls(&mut Cursor::new(client_to_server)).unwrap();
+                server.process_new_packets()?;
+            }
+
+            let mut server_to_client = Vec::new();
+            if server.wants_write() {
+                server.write_tls(&mut server_to_client).unwrap();
+            }
+            if !server_to_client.is_empty() {
+                client.read_tls(&mut Cursor::new(server_to_client)).unwrap();
+                client.process_new_packets().unwrap();
+            }
+        }
+
+        Ok((client, server))
+    }
+
+    #[test]
+    fn peer_certificate_is_available_after_handshake() {
+        let server_key = include_str!("../examples/tls/key.rsa");
+        let server_cert = include_str!("../examples/tls/cert.pem");
+        let client_key = include_str!("../examples/tls/client_key.rsa");
+        let client_cert = include_str!("../examples/tls/client_cert.pem");
+
+        let server_config = TlsConfigBuilder::new()
+            .key(server_key.as_bytes())
+            .cert(server_cert.as_bytes())
+            .client_auth_required(client_cert.as_bytes())
+            .build()
+            .unwrap();
+        let client_config = ClientConfig::builder()
+            .with_root_certificates(trust_roots(&[server_cert.as_bytes()]))
+            .with_client_auth_cert(
+                parse_cert_chain(Box::new(Cursor::new(Vec::from(client_cert.as_bytes())))).unwrap(),
+                parse_private_key(Box::new(Cursor::new(Vec::from(client_key.as_bytes())))).unwrap(),
+            )
+            .unwrap();
+
+        // This is exactly what `TlsStream::capture_peer_certs` relies on to
+        // surface the client's identity through `Transport::peer_certificates()`.
+        let (_client, server) = handshake(
+            Arc::new(server_config),
+            Arc::new(client_config),
+            "localhost",
+        )
+        .expect("handshake should succeed for a trusted, non-revoked client cert");
+        assert!(server.peer_certificates().is_some());
+    }
 
     #[test]
     fn file_cert_key() {
@@ -403,6 +806,87 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn default_alpn_protocols_advertise_h2_and_http11() {
+        let key = include_str!("../examples/tls/key.rsa");
+        let cert = include_str!("../examples/tls/cert.pem");
+
+        let config = TlsConfigBuilder::new()
+            .key(key.as_bytes())
+            .cert(cert.as_bytes())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            config.alpn_protocols,
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+    }
+
+    #[test]
+    fn disable_http2_only_advertises_http11() {
+        let key = include_str!("../examples/tls/key.rsa");
+        let cert = include_str!("../examples/tls/cert.pem");
+
+        let config = TlsConfigBuilder::new()
+            .key(key.as_bytes())
+            .cert(cert.as_bytes())
+            .disable_http2()
+            .build()
+            .unwrap();
+
+        assert_eq!(config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+    }
+
+    #[test]
+    fn alpn_protocols_overrides_the_default() {
+        let key = include_str!("../examples/tls/key.rsa");
+        let cert = include_str!("../examples/tls/cert.pem");
+
+        let config = TlsConfigBuilder::new()
+            .key(key.as_bytes())
+            .cert(cert.as_bytes())
+            .alpn_protocols(vec![b"custom/1".to_vec()])
+            .build()
+            .unwrap();
+
+        assert_eq!(config.alpn_protocols, vec![b"custom/1".to_vec()]);
+    }
+
+    #[test]
+    fn revoked_client_certificate_is_rejected() {
+        let server_key = include_str!("../examples/tls/key.rsa");
+        let server_cert = include_str!("../examples/tls/cert.pem");
+        let client_key = include_str!("../examples/tls/client_key.rsa");
+        let client_cert = include_str!("../examples/tls/client_cert.pem");
+        let crl = include_str!("../examples/tls/client.crl");
+
+        let server_config = TlsConfigBuilder::new()
+            .key(server_key.as_bytes())
+            .cert(server_cert.as_bytes())
+            .client_auth_required(client_cert.as_bytes())
+            .client_auth_crl(crl.as_bytes())
+            .build()
+            .unwrap();
+        let client_config = ClientConfig::builder()
+            .with_root_certificates(trust_roots(&[server_cert.as_bytes()]))
+            .with_client_auth_cert(
+                parse_cert_chain(Box::new(Cursor::new(Vec::from(client_cert.as_bytes())))).unwrap(),
+                parse_private_key(Box::new(Cursor::new(Vec::from(client_key.as_bytes())))).unwrap(),
+            )
+            .unwrap();
+
+        let result = handshake(
+            Arc::new(server_config),
+            Arc::new(client_config),
+            "localhost",
+        );
+        assert!(
+            result.is_err(),
+            "a revoked client certificate must fail verification"
+        );
+    }
+
     #[test]
     fn file_ecc_cert_key() {
         TlsConfigBuilder::new()
@@ -424,6 +908,100 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn sni_cert_with_default_fallback() {
+        let key = include_str!("../examples/tls/key.rsa");
+        let cert = include_str!("../examples/tls/cert.pem");
+        let sni_key = include_str!("../examples/tls/key.ecc");
+        let sni_cert = include_str!("../examples/tls/cert.ecc.pem");
+
+        // A default cert/key is still configured alongside `add_sni_cert`, so
+        // clients that don't match any registered hostname fall back to it
+        // instead of failing the handshake.
+        TlsConfigBuilder::new()
+            .key(key.as_bytes())
+            .cert(cert.as_bytes())
+            .add_sni_cert("example.com", sni_cert.as_bytes(), sni_key.as_bytes())
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn sni_cert_without_default() {
+        let sni_key = include_str!("../examples/tls/key.ecc");
+        let sni_cert = include_str!("../examples/tls/cert.ecc.pem");
+
+        // No default cert/key configured: SNI mode must still build, just
+        // without a fallback for unmatched hostnames.
+        TlsConfigBuilder::new()
+            .add_sni_cert("example.com", sni_cert.as_bytes(), sni_key.as_bytes())
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn sni_hostname_selects_the_matching_certificate() {
+        let default_key = include_str!("../examples/tls/key.rsa");
+        let default_cert = include_str!("../examples/tls/cert.pem");
+        let sni_key = include_str!("../examples/tls/key.ecc");
+        let sni_cert = include_str!("../examples/tls/cert.ecc.pem");
+
+        let server_config = Arc::new(
+            TlsConfigBuilder::new()
+                .key(default_key.as_bytes())
+                .cert(default_cert.as_bytes())
+                .add_sni_cert("sni.example.com", sni_cert.as_bytes(), sni_key.as_bytes())
+                .build()
+                .unwrap(),
+        );
+        let client_config = Arc::new(
+            ClientConfig::builder()
+                .with_root_certificates(trust_roots(&[
+                    default_cert.as_bytes(),
+                    sni_cert.as_bytes(),
+                ]))
+                .with_no_client_auth(),
+        );
+
+        // Drives a handshake against a chosen SNI name and returns the
+        // certificate chain the server ended up presenting.
+        let presented_cert = |server_name: &str| -> Vec<CertificateDer<'static>> {
+            let (client, _server) =
+                handshake(server_config.clone(), client_config.clone(), server_name).unwrap();
+            client.peer_certificates().unwrap().to_vec()
+        };
+
+        let matched = presented_cert("sni.example.com");
+        let fell_back = presented_cert("localhost");
+        assert_ne!(
+            matched, fell_back,
+            "a registered SNI hostname must get its own certificate, not the default"
+        );
+    }
+
+    #[test]
+    fn reload_preserves_settings() {
+        let key = include_str!("../examples/tls/key.rsa");
+        let cert = include_str!("../examples/tls/cert.pem");
+
+        let (_config, template) = TlsConfigBuilder::new()
+            .key(key.as_bytes())
+            .cert(cert.as_bytes())
+            .disable_http2()
+            .client_auth_optional(cert.as_bytes())
+            .build_with_template()
+            .unwrap();
+
+        // A TlsReloadHandle::reload rebuilds through the template rather
+        // than a fresh TlsConfigBuilder, so settings like disable_http2 and
+        // client_auth_optional must survive even though only a new cert/key
+        // is supplied.
+        let reloaded = template
+            .build_server_config(cert.as_bytes(), key.as_bytes(), Vec::new())
+            .unwrap();
+        assert_eq!(reloaded.alpn_protocols, vec![b"http/1.1".to_vec()]);
+    }
+
     #[test]
     fn cert_key_as_one() {
         let key = include_str!("../examples/tls/key.ecc");
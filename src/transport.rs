@@ -0,0 +1,22 @@
+use std::net::SocketAddr;
+
+use tokio_rustls::rustls::pki_types::CertificateDer;
+
+/// An asynchronous transport that a connection was accepted over.
+///
+/// Filters like [`warp::filters::addr::remote`] and [`warp::tls::peer_certificates`]
+/// read from this trait to expose connection-level information without caring
+/// whether the underlying stream is a plain TCP socket or a Tls stream.
+pub(crate) trait Transport {
+    /// Returns the remote address that this transport is connected to, if known.
+    fn remote_addr(&self) -> Option<SocketAddr>;
+
+    /// Returns the peer's Tls client certificate chain, if the transport is
+    /// Tls-encrypted and the peer presented one.
+    ///
+    /// Transports that aren't Tls-encrypted, or that didn't capture the peer's
+    /// certificate, return `None`.
+    fn peer_certificates(&self) -> Option<Vec<CertificateDer<'static>>> {
+        None
+    }
+}